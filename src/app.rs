@@ -3,6 +3,10 @@ use image::{imageops::FilterType, DynamicImage, GenericImageView, Pixel};
 use log::{debug, info, warn};
 use std::{ffi::OsStr, fmt::Display, io::Write, path::PathBuf};
 
+use crate::crc32;
+use crate::packbits;
+use crate::quantize;
+
 #[derive(Debug, Default, Clone, Copy, ValueEnum, PartialEq, Eq)]
 enum ColorType {
     /// 3 bytes per pixel
@@ -20,6 +24,12 @@ enum ColorType {
     SSD1306,
     ///
     GCode,
+    /// Byte-oriented RLE (QuickDraw PICT PackBits)
+    PackBits,
+    /// Packed 16-bit color. 2 bytes per pixel
+    Rgb565,
+    /// 1 byte per pixel, indexed into a quantized palette
+    Indexed,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum, Default)]
@@ -85,6 +95,13 @@ enum Ending {
     Be,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, Default, PartialEq, Eq)]
+enum Checksum {
+    #[default]
+    None,
+    Crc32,
+}
+
 #[derive(Parser, Debug)]
 struct Arg {
     #[arg(help = "Input image")]
@@ -122,6 +139,47 @@ struct Arg {
 
     #[arg(long, help = "Ending out pixel", default_value = "le")]
     ending: Ending,
+
+    #[arg(
+        long,
+        help = "Emit a checksum constant over the output byte buffer",
+        default_value = "none"
+    )]
+    checksum: Checksum,
+
+    #[arg(
+        long,
+        help = "Max palette size for indexed out-color (<= 256)",
+        default_value = "256"
+    )]
+    max_colors: u16,
+
+    #[arg(
+        long,
+        help = "Decode the encoded buffer back into a PNG for a visual round-trip check"
+    )]
+    preview_out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Max S-word laser/spindle power for GCode out-color",
+        default_value = "255"
+    )]
+    gcode_max_power: u16,
+
+    #[arg(
+        long,
+        help = "Feed rate (F word) for GCode raster moves",
+        default_value = "1000"
+    )]
+    gcode_feed: u32,
+
+    #[arg(
+        long,
+        help = "Millimeters per pixel for GCode raster moves",
+        default_value = "1.0"
+    )]
+    gcode_mm_per_pixel: f32,
 }
 
 pub struct App {
@@ -164,6 +222,11 @@ impl App {
         if let Some(ni) = self.resize(&image) {
             image = ni;
         }
+        let indexed_palette = (self.args.out_color == ColorType::Indexed).then(|| {
+            let pixels: Vec<[u8; 3]> = image.to_rgb8().pixels().map(|p| p.0).collect();
+            quantize::median_cut(&pixels, self.args.max_colors as usize)
+        });
+
         let (step, mut img_buffer, width_del) = match self.args.out_color {
             ColorType::GCode => (1usize, image.to_luma8().into_vec(), 1),
             ColorType::Rgb8 => (3usize, image.to_rgb8().into_vec(), 1),
@@ -178,6 +241,33 @@ impl App {
                 1,
             ),
             ColorType::Gray8 => (1, image.to_luma8().into_vec(), 1),
+            ColorType::PackBits => (1, image.to_luma8().into_vec(), 1),
+            ColorType::Rgb565 => (
+                2,
+                image
+                    .to_rgb8()
+                    .into_vec()
+                    .chunks(3)
+                    .flat_map(|p| {
+                        let value = ((p[0] as u16 >> 3) << 11)
+                            | ((p[1] as u16 >> 2) << 5)
+                            | (p[2] as u16 >> 3);
+                        match self.args.ending {
+                            Ending::Le => value.to_le_bytes(),
+                            Ending::Be => value.to_be_bytes(),
+                        }
+                    })
+                    .collect(),
+                1,
+            ),
+            ColorType::Indexed => (
+                1,
+                indexed_palette
+                    .as_ref()
+                    .map(|(_, indices)| indices.clone())
+                    .unwrap_or_default(),
+                1,
+            ),
             ColorType::WBZip => (
                 1,
                 image
@@ -210,6 +300,12 @@ impl App {
         };
         let mut fout = std::fs::File::create(&self.args.output)?;
 
+        if self.args.out_color == ColorType::GCode {
+            self.write_gcode(&mut fout, &image, &img_buffer)?;
+            fout.sync_data()?;
+            return Ok(());
+        }
+
         if self.args.out_lang == OutLang::C {
             let p = self.args.protect.as_ref().unwrap_or_else(|| &image_name);
             writeln!(fout, "#ifndef __{}", p)?;
@@ -262,6 +358,8 @@ impl App {
 
         match self.args.out_color {
             ColorType::WBZip => {}
+            ColorType::PackBits => {}
+            ColorType::Indexed => {}
             _ => match self.args.out_lang {
                 OutLang::C => writeln!(fout, "uint8_t {}[{}_LENGTH] = {{", image_name, image_name)?,
                 OutLang::Rust => writeln!(
@@ -272,6 +370,9 @@ impl App {
             },
         }
 
+        let mut checksum_source = img_buffer.clone();
+        let mut preview_source = img_buffer.clone();
+
         match self.args.out_color {
             ColorType::WBZip => {
                 let mut buff = Vec::new();
@@ -322,6 +423,8 @@ impl App {
                         OutputPreview::Bin => write!(fout, "0b{:08b}, ", self.to_ending(p))?,
                     }
                 }
+                preview_source = buff.clone();
+                checksum_source = buff;
             }
             ColorType::SSD1306 => {
                 img_buffer.iter_mut().for_each(|v| {
@@ -348,12 +451,75 @@ impl App {
                         }
                     }
                 }
+                preview_source = img_buffer.clone();
+                checksum_source = img_buffer.clone();
             }
-            ColorType::GCode=>{
-                for (i, p) in img_buffer.chunks(step).enumerate() {
-                    todo!("тут");
+            ColorType::PackBits => {
+                let buff = packbits::encode(&img_buffer);
+                match self.args.out_lang {
+                    OutLang::C => writeln!(fout, "uint8_t {}[{}] = {{", image_name, buff.len())?,
+                    OutLang::Rust => {
+                        writeln!(fout, "pub const {}: [u8; {}] = [", image_name, buff.len())?
+                    }
                 }
+                for p in &buff {
+                    match self.args.output_view {
+                        OutputPreview::Hex => write!(fout, "0x{:02x}, ", self.to_ending(p))?,
+                        OutputPreview::Dec => write!(fout, "{:3}, ", self.to_ending(p))?,
+                        OutputPreview::SDec => write!(fout, "{:3}, ", *p as u16 as i8)?,
+                        OutputPreview::Bin => write!(fout, "0b{:08b}, ", self.to_ending(p))?,
+                    }
+                }
+                preview_source = buff.clone();
+                checksum_source = buff;
             }
+            ColorType::Indexed => {
+                let (palette, _) = indexed_palette
+                    .as_ref()
+                    .expect("indexed_palette computed for ColorType::Indexed");
+                let palette_name = format!("{}_PALETTE", image_name);
+                let palette_bytes: Vec<u8> =
+                    palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect();
+
+                self.write_const_type(
+                    &mut fout,
+                    &format!("{}_LENGTH", palette_name),
+                    palette_bytes.len(),
+                    "usize",
+                )?;
+                match self.args.out_lang {
+                    OutLang::C => {
+                        writeln!(fout, "uint8_t {}[{}_LENGTH] = {{", palette_name, palette_name)?
+                    }
+                    OutLang::Rust => writeln!(
+                        fout,
+                        "pub const {}: [u8; {}_LENGTH] = [",
+                        palette_name, palette_name
+                    )?,
+                }
+                self.write_values(&mut fout, &palette_bytes)?;
+                match self.args.out_lang {
+                    OutLang::C => writeln!(fout, "}};\n")?,
+                    OutLang::Rust => writeln!(fout, "];\n")?,
+                }
+
+                match self.args.out_lang {
+                    OutLang::C => writeln!(fout, "uint8_t {}[{}_LENGTH] = {{", image_name, image_name)?,
+                    OutLang::Rust => writeln!(
+                        fout,
+                        "pub const {}: [u8; {}_LENGTH] = [",
+                        image_name, image_name
+                    )?,
+                }
+                self.write_values(&mut fout, &img_buffer)?;
+
+                checksum_source = palette_bytes
+                    .iter()
+                    .chain(img_buffer.iter())
+                    .copied()
+                    .collect();
+            }
+            ColorType::GCode => unreachable!("GCode output is streamed directly in work()"),
             _ => {
                 for (i, p) in img_buffer.chunks(step).enumerate() {
                     for p in p {
@@ -377,12 +543,26 @@ impl App {
             OutLang::Rust => writeln!(fout, "];")?,
         }
 
+        if self.args.checksum == Checksum::Crc32 {
+            let crc = crc32::checksum(&checksum_source);
+            self.write_const_type(&mut fout, &format!("{}_CRC32", image_name), crc, "u32")?;
+        }
+
         if self.args.out_lang == OutLang::C {
             let p = self.args.protect.as_ref().unwrap_or_else(|| &image_name);
             writeln!(fout, "#endif //__{}", p)?;
         }
 
         fout.sync_data()?;
+
+        if self.args.preview_out.is_some() {
+            self.write_preview(
+                &image,
+                &preview_source,
+                indexed_palette.as_ref().map(|(p, _)| p.as_slice()),
+            )?;
+        }
+
         Ok(())
     }
 
@@ -402,6 +582,234 @@ impl App {
         }
     }
 
+    fn write_preview(
+        &self,
+        image: &DynamicImage,
+        bytes: &[u8],
+        palette: Option<&[[u8; 3]]>,
+    ) -> anyhow::Result<()> {
+        let Some(path) = self.args.preview_out.clone() else {
+            return Ok(());
+        };
+
+        let width = image.width();
+        let height = image.height();
+
+        let decoded: DynamicImage = match self.args.out_color {
+            ColorType::Rgb8 => DynamicImage::ImageRgb8(
+                image::RgbImage::from_vec(width, height, bytes.to_vec())
+                    .ok_or_else(|| anyhow::anyhow!("rgb8 buffer does not match image size"))?,
+            ),
+            ColorType::Rgb16 => {
+                let pixels: Vec<u8> = bytes
+                    .chunks(2)
+                    .map(|c| (u16::from_le_bytes([c[0], c[1]]) >> 8) as u8)
+                    .collect();
+                DynamicImage::ImageRgb8(
+                    image::RgbImage::from_vec(width, height, pixels)
+                        .ok_or_else(|| anyhow::anyhow!("rgb16 buffer does not match image size"))?,
+                )
+            }
+            ColorType::Gray8 => DynamicImage::ImageLuma8(
+                image::GrayImage::from_vec(width, height, bytes.to_vec())
+                    .ok_or_else(|| anyhow::anyhow!("gray8 buffer does not match image size"))?,
+            ),
+            ColorType::PackBits => {
+                let luma = packbits::decode(bytes);
+                DynamicImage::ImageLuma8(
+                    image::GrayImage::from_vec(width, height, luma).ok_or_else(|| {
+                        anyhow::anyhow!("packbits buffer does not match image size")
+                    })?,
+                )
+            }
+            ColorType::WB1 => {
+                let mut luma = Vec::with_capacity((width * height) as usize);
+                for byte in bytes {
+                    // A short final chunk is packed into the low bits.
+                    let remaining = (width * height) as usize - luma.len();
+                    for bit in (0..remaining.min(8)).rev() {
+                        luma.push(if (byte >> bit) & 1 == 1 { 255 } else { 0 });
+                    }
+                }
+                DynamicImage::ImageLuma8(
+                    image::GrayImage::from_vec(width, height, luma)
+                        .ok_or_else(|| anyhow::anyhow!("wb1 buffer does not match image size"))?,
+                )
+            }
+            ColorType::SSD1306 => {
+                let mut luma = vec![0u8; (width * height) as usize];
+                let pages = (height as f32 / 8.0).ceil() as u32;
+                for page in 0..pages {
+                    for col in 0..width {
+                        let Some(&byte) = bytes.get((page * width + col) as usize) else {
+                            continue;
+                        };
+                        for bit in 0..8 {
+                            let row = page * 8 + bit;
+                            if row >= height {
+                                continue;
+                            }
+                            luma[(row * width + col) as usize] =
+                                if (byte >> bit) & 1 == 1 { 255 } else { 0 };
+                        }
+                    }
+                }
+                DynamicImage::ImageLuma8(
+                    image::GrayImage::from_vec(width, height, luma).ok_or_else(|| {
+                        anyhow::anyhow!("ssd1306 buffer does not match image size")
+                    })?,
+                )
+            }
+            ColorType::WBZip => {
+                let run_bytes = &bytes[2.min(bytes.len())..];
+                let mut luma = Vec::with_capacity((width * height) as usize);
+                for &header in run_bytes {
+                    let color = header & 0b1000_0000 != 0;
+                    let count = (header & 0b0111_1111) as usize + 1;
+                    for _ in 0..count {
+                        luma.push(if color { 255 } else { 0 });
+                    }
+                }
+                luma.resize((width * height) as usize, 0);
+                DynamicImage::ImageLuma8(
+                    image::GrayImage::from_vec(width, height, luma)
+                        .ok_or_else(|| anyhow::anyhow!("wbzip buffer does not match image size"))?,
+                )
+            }
+            ColorType::Rgb565 => {
+                let pixels: Vec<u8> = bytes
+                    .chunks(2)
+                    .flat_map(|c| {
+                        let value = match self.args.ending {
+                            Ending::Le => u16::from_le_bytes([c[0], c[1]]),
+                            Ending::Be => u16::from_be_bytes([c[0], c[1]]),
+                        };
+                        let r = ((value >> 11) & 0x1F) as u8;
+                        let g = ((value >> 5) & 0x3F) as u8;
+                        let b = (value & 0x1F) as u8;
+                        [r << 3, g << 2, b << 3]
+                    })
+                    .collect();
+                DynamicImage::ImageRgb8(
+                    image::RgbImage::from_vec(width, height, pixels)
+                        .ok_or_else(|| anyhow::anyhow!("rgb565 buffer does not match image size"))?,
+                )
+            }
+            ColorType::Indexed => {
+                let palette = palette
+                    .ok_or_else(|| anyhow::anyhow!("no palette available to decode indexed output"))?;
+                let pixels: Vec<u8> = bytes
+                    .iter()
+                    .flat_map(|&idx| palette.get(idx as usize).copied().unwrap_or([0, 0, 0]))
+                    .collect();
+                DynamicImage::ImageRgb8(
+                    image::RgbImage::from_vec(width, height, pixels)
+                        .ok_or_else(|| anyhow::anyhow!("indexed buffer does not match image size"))?,
+                )
+            }
+            ColorType::GCode => {
+                warn!("Preview is not supported for GCode output");
+                return Ok(());
+            }
+        };
+
+        decoded.save(&path)?;
+
+        let source_luma = image.to_luma8();
+        let decoded_luma = decoded.to_luma8();
+        let mae = source_luma
+            .iter()
+            .zip(decoded_luma.iter())
+            .map(|(a, b)| (*a as f32 - *b as f32).abs())
+            .sum::<f32>()
+            / source_luma.len().max(1) as f32;
+        info!("Preview mean absolute error vs source: {:.3}", mae);
+
+        Ok(())
+    }
+
+    fn write_gcode(
+        &self,
+        fout: &mut std::fs::File,
+        image: &DynamicImage,
+        luma: &[u8],
+    ) -> anyhow::Result<()> {
+        let width = image.width();
+        let height = image.height();
+        let mm_per_pixel = self.args.gcode_mm_per_pixel;
+        let feed = self.args.gcode_feed;
+
+        writeln!(fout, "G21")?;
+        writeln!(fout, "G90")?;
+        writeln!(fout, "M3 S0")?;
+
+        if width == 0 || height == 0 {
+            writeln!(fout, "M5")?;
+            return Ok(());
+        }
+
+        for y in 0..height {
+            let row = &luma[(y * width) as usize..((y + 1) * width) as usize];
+            let order: Vec<u32> = if y % 2 == 0 {
+                (0..width).collect()
+            } else {
+                (0..width).rev().collect()
+            };
+
+            let start_x = order[0];
+            let mut run_power = self.gcode_power(row[start_x as usize]);
+            writeln!(
+                fout,
+                "G1 X{:.3} Y{:.3} F{} S{}",
+                start_x as f32 * mm_per_pixel,
+                y as f32 * mm_per_pixel,
+                feed,
+                run_power
+            )?;
+
+            for pair in order.windows(2) {
+                let (prev_x, next_x) = (pair[0], pair[1]);
+                let next_power = self.gcode_power(row[next_x as usize]);
+                if next_power != run_power {
+                    writeln!(
+                        fout,
+                        "G1 X{:.3} Y{:.3} F{} S{}",
+                        prev_x as f32 * mm_per_pixel,
+                        y as f32 * mm_per_pixel,
+                        feed,
+                        run_power
+                    )?;
+                    run_power = next_power;
+                }
+            }
+
+            if order.len() > 1 {
+                let end_x = *order.last().expect("row has at least one pixel");
+                writeln!(
+                    fout,
+                    "G1 X{:.3} Y{:.3} F{} S{}",
+                    end_x as f32 * mm_per_pixel,
+                    y as f32 * mm_per_pixel,
+                    feed,
+                    run_power
+                )?;
+            }
+        }
+
+        writeln!(fout, "M5")?;
+        Ok(())
+    }
+
+    fn gcode_power(&self, luma: u8) -> u16 {
+        let black_level = self.args.black_level as f32;
+        let darkness = if luma as f32 <= black_level {
+            1.0
+        } else {
+            (255.0 - luma as f32) / (255.0 - black_level).max(1.0)
+        };
+        (darkness.clamp(0.0, 1.0) * self.args.gcode_max_power as f32).round() as u16
+    }
+
     fn write_const<V: Display>(
         &self,
         fout: &mut std::fs::File,
@@ -429,6 +837,18 @@ impl App {
         Ok(())
     }
 
+    fn write_values(&self, fout: &mut std::fs::File, values: &[u8]) -> anyhow::Result<()> {
+        for v in values {
+            match self.args.output_view {
+                OutputPreview::Hex => write!(fout, "0x{:02x}, ", self.to_ending(v))?,
+                OutputPreview::Dec => write!(fout, "{:3}, ", self.to_ending(v))?,
+                OutputPreview::SDec => write!(fout, "{:3}, ", *v as u16 as i8)?,
+                OutputPreview::Bin => write!(fout, "0b{:08b}, ", self.to_ending(v))?,
+            }
+        }
+        Ok(())
+    }
+
     fn to_ending<T: ToOrder>(&self, v: &T) -> u8 {
         match self.args.ending {
             Ending::Le => v.le(),
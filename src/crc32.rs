@@ -0,0 +1,25 @@
+//! Standard reflected CRC-32 (the variant used by zlib/PNG/Ethernet).
+
+const POLY: u32 = 0xEDB8_8320;
+
+fn table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    for n in 0..256u32 {
+        let mut c = n;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { POLY ^ (c >> 1) } else { c >> 1 };
+        }
+        table[n as usize] = c;
+    }
+    table
+}
+
+/// Compute the CRC32 checksum of `bytes`.
+pub fn checksum(bytes: &[u8]) -> u32 {
+    let table = table();
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in bytes {
+        crc = table[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xFFFF_FFFF
+}
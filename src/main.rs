@@ -1,4 +1,7 @@
 mod app;
+mod crc32;
+mod packbits;
+mod quantize;
 
 fn main() -> anyhow::Result<()> {
     let mut app = app::App::new();
@@ -0,0 +1,70 @@
+//! Byte-oriented run-length encoding, as used by QuickDraw PICT (PackBits).
+
+/// Encode `data` into PackBits packets.
+///
+/// Emits literal packets (header `0..=127` meaning "copy the next
+/// `header + 1` bytes verbatim") and repeat packets (header stored as
+/// `257 - count` for `count` in `3..=128`, meaning "repeat the following
+/// byte `count` times"; shorter runs stay in literal packets). The no-op
+/// header `0x80` is never produced.
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    let mut literal_start = 0usize;
+
+    fn flush_literal(out: &mut Vec<u8>, data: &[u8], mut start: usize, end: usize) {
+        while start < end {
+            let len = (end - start).min(128);
+            out.push((len - 1) as u8);
+            out.extend_from_slice(&data[start..start + len]);
+            start += len;
+        }
+    }
+
+    while i < data.len() {
+        let mut run_len = 1;
+        while i + run_len < data.len() && run_len < 128 && data[i + run_len] == data[i] {
+            run_len += 1;
+        }
+
+        if run_len >= 3 {
+            flush_literal(&mut out, data, literal_start, i);
+            out.push((257 - run_len) as u8);
+            out.push(data[i]);
+            i += run_len;
+            literal_start = i;
+        } else {
+            i += 1;
+        }
+    }
+    flush_literal(&mut out, data, literal_start, data.len());
+
+    out
+}
+
+/// Decode a PackBits-encoded buffer back into its original bytes.
+pub fn decode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let header = data[i];
+        i += 1;
+        match header {
+            0..=127 => {
+                let len = header as usize + 1;
+                out.extend_from_slice(&data[i..i + len]);
+                i += len;
+            }
+            0x80 => {}
+            _ => {
+                let count = 257usize - header as usize;
+                let byte = data[i];
+                i += 1;
+                for _ in 0..count {
+                    out.push(byte);
+                }
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,92 @@
+//! Median-cut color quantization for indexed-palette output.
+
+struct ColorBox {
+    pixels: Vec<[u8; 3]>,
+}
+
+impl ColorBox {
+    fn channel_range(&self, channel: usize) -> u8 {
+        let mut min = 255u8;
+        let mut max = 0u8;
+        for p in &self.pixels {
+            min = min.min(p[channel]);
+            max = max.max(p[channel]);
+        }
+        max - min
+    }
+
+    fn longest_channel(&self) -> usize {
+        (0..3)
+            .max_by_key(|&c| self.channel_range(c))
+            .unwrap_or(0)
+    }
+
+    fn mean(&self) -> [u8; 3] {
+        let mut sum = [0u32; 3];
+        for p in &self.pixels {
+            for (s, &c) in sum.iter_mut().zip(p.iter()) {
+                *s += c as u32;
+            }
+        }
+        let n = self.pixels.len().max(1) as u32;
+        [
+            (sum[0] / n) as u8,
+            (sum[1] / n) as u8,
+            (sum[2] / n) as u8,
+        ]
+    }
+
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let channel = self.longest_channel();
+        self.pixels.sort_by_key(|p| p[channel]);
+        let second = self.pixels.split_off(self.pixels.len() / 2);
+        (ColorBox { pixels: self.pixels }, ColorBox { pixels: second })
+    }
+}
+
+/// Quantize `pixels` into at most `max_colors` palette entries using
+/// median-cut: repeatedly split the box whose longest channel range is
+/// largest at the median of that channel, until `max_colors` boxes exist.
+/// Returns the palette (mean color of each box) and, for every input
+/// pixel, the index of the palette entry it was mapped to.
+pub fn median_cut(pixels: &[[u8; 3]], max_colors: usize) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let max_colors = max_colors.clamp(1, 256);
+    let mut boxes = vec![ColorBox {
+        pixels: pixels.to_vec(),
+    }];
+
+    while boxes.len() < max_colors {
+        let split_idx = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.pixels.len() >= 2)
+            .max_by_key(|(_, b)| b.channel_range(b.longest_channel()))
+            .map(|(i, _)| i);
+
+        let Some(idx) = split_idx else { break };
+        let (a, b) = boxes.remove(idx).split();
+        boxes.push(a);
+        boxes.push(b);
+    }
+
+    let palette: Vec<[u8; 3]> = boxes.iter().map(ColorBox::mean).collect();
+
+    let indices = pixels
+        .iter()
+        .map(|p| {
+            palette
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, c)| {
+                    let dr = p[0] as i32 - c[0] as i32;
+                    let dg = p[1] as i32 - c[1] as i32;
+                    let db = p[2] as i32 - c[2] as i32;
+                    dr * dr + dg * dg + db * db
+                })
+                .map(|(i, _)| i as u8)
+                .unwrap_or(0)
+        })
+        .collect();
+
+    (palette, indices)
+}